@@ -15,12 +15,17 @@ use once_cell::sync::OnceCell;
 use psst_core::{
     access_token::TokenProvider, session::SessionService, util::default_ureq_agent_builder,
 };
-use serde::{de::DeserializeOwned, Deserialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     fmt::Display,
     io::{self, Read},
+    marker::PhantomData,
     path::PathBuf,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     thread,
     time::Duration,
 };
@@ -28,11 +33,368 @@ use ureq::{Agent, Request, Response};
 
 use super::cache::WebApiCache;
 
+/// Host for the public, documented Web API.
+const API_HOST: &str = "api.spotify.com";
+/// Host for Spotify's internal client endpoints, such as time-synced lyrics,
+/// that are not part of the public Web API.
+const SPCLIENT_HOST: &str = "spclient.wg.spotify.com";
+
+/// A Spotify id failed to parse as a 22-character base62 id, a
+/// `spotify:kind:id` URI, or an `open.spotify.com/kind/id` URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidId;
+
+impl Display for InvalidId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid Spotify id")
+    }
+}
+
+impl std::error::Error for InvalidId {}
+
+impl From<InvalidId> for Error {
+    fn from(err: InvalidId) -> Self {
+        Error::WebApiError(err.to_string())
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Tags an [`Id`] with the kind of item it refers to.
+pub trait IdKind: sealed::Sealed {
+    const KIND: &'static str;
+}
+
+macro_rules! id_kind {
+    ($name:ident, $kind:literal) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct $name;
+
+        impl sealed::Sealed for $name {}
+
+        impl IdKind for $name {
+            const KIND: &'static str = $kind;
+        }
+    };
+}
+
+id_kind!(ArtistIdKind, "artist");
+id_kind!(AlbumIdKind, "album");
+id_kind!(TrackIdKind, "track");
+id_kind!(PlaylistIdKind, "playlist");
+id_kind!(UserIdKind, "user");
+id_kind!(EpisodeIdKind, "episode");
+
+/// A Spotify id of a specific kind, either borrowed from a `spotify:kind:id`
+/// URI / `open.spotify.com` URL, or owned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Id<'a, K> {
+    id: Cow<'a, str>,
+    _kind: PhantomData<K>,
+}
+
+pub type ArtistId<'a> = Id<'a, ArtistIdKind>;
+pub type AlbumId<'a> = Id<'a, AlbumIdKind>;
+pub type TrackId<'a> = Id<'a, TrackIdKind>;
+pub type PlaylistId<'a> = Id<'a, PlaylistIdKind>;
+pub type UserId<'a> = Id<'a, UserIdKind>;
+pub type EpisodeId<'a> = Id<'a, EpisodeIdKind>;
+
+impl<'a, K: IdKind> Id<'a, K> {
+    /// Parse a bare base62 id, a `spotify:kind:id` URI, or an
+    /// `open.spotify.com/kind/id` URL.
+    ///
+    /// Named `parse` rather than `from_str` so it isn't mistaken for (and
+    /// doesn't collide with) `std::str::FromStr::from_str`: `Id` borrows its
+    /// lifetime from the input, which `FromStr` has no room for. The rename
+    /// alone already keeps clippy's `should_implement_trait` from firing,
+    /// since that lint only matches a fixed set of method names.
+    pub fn parse(s: &'a str) -> Result<Self, InvalidId> {
+        let bare = if let Some(rest) = s.strip_prefix("spotify:") {
+            let mut parts = rest.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(kind), Some(id)) if kind == K::KIND => id,
+                _ => return Err(InvalidId),
+            }
+        } else if let Some(rest) = s.split("open.spotify.com/").nth(1) {
+            let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+            let mut parts = rest.splitn(2, '/');
+            match (parts.next(), parts.next()) {
+                (Some(kind), Some(id)) if kind == K::KIND => id,
+                _ => return Err(InvalidId),
+            }
+        } else {
+            s
+        };
+        Self::from_bare(Cow::Borrowed(bare))
+    }
+
+    /// Wrap an already-bare base62 id, such as one already extracted by
+    /// [`SpotifyUrl`]'s own URI/URL parsing, without re-running the
+    /// `spotify:`/`open.spotify.com` detection in [`Id::parse`].
+    pub fn from_bare_id(id: &'a str) -> Result<Self, InvalidId> {
+        Self::from_bare(Cow::Borrowed(id))
+    }
+
+    /// Wrap an already-owned bare base62 id.
+    pub fn from_owned(id: String) -> Result<Self, InvalidId> {
+        Self::from_bare(Cow::Owned(id))
+    }
+
+    fn from_bare(id: Cow<'a, str>) -> Result<Self, InvalidId> {
+        if id.len() == 22 && id.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            Ok(Self {
+                id,
+                _kind: PhantomData,
+            })
+        } else {
+            Err(InvalidId)
+        }
+    }
+
+    /// The bare base62 id, with no `spotify:kind:` prefix.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn to_base62(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl<K> Display for Id<'_, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+/// A context that tracks can be played from: an artist, an album, or a
+/// playlist.
+#[derive(Clone, Debug)]
+pub enum PlayContext<'a> {
+    Artist(ArtistId<'a>),
+    Album(AlbumId<'a>),
+    Playlist(PlaylistId<'a>),
+}
+
+/// An item that can be played or queued: a track or an episode.
+#[derive(Clone, Debug)]
+pub enum Playable<'a> {
+    Track(TrackId<'a>),
+    Episode(EpisodeId<'a>),
+}
+
+impl Playable<'_> {
+    pub fn uri(&self) -> String {
+        match self {
+            Playable::Track(id) => format!("spotify:track:{}", id),
+            Playable::Episode(id) => format!("spotify:episode:{}", id),
+        }
+    }
+}
+
+/// Time-synced (or unsynced) lyrics for a track, as returned by the
+/// color-lyrics endpoint.
+#[derive(Clone, Data, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lyrics {
+    pub sync_type: LyricsSyncType,
+    pub lines: Vector<LyricsLine>,
+    pub provider: Option<Arc<str>>,
+    pub language: Option<Arc<str>>,
+}
+
+#[derive(Clone, Copy, Data, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LyricsSyncType {
+    LineSynced,
+    Unsynced,
+}
+
+#[derive(Clone, Data, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricsLine {
+    #[serde(deserialize_with = "deserialize_ms_string")]
+    pub start_time_ms: u32,
+    pub words: Arc<str>,
+}
+
+fn deserialize_ms_string<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    // `String`, not `&str`: `load_cached` deserializes cache hits with
+    // `serde_json::from_reader`, which cannot produce a borrowed string.
+    let value: String = Deserialize::deserialize(deserializer)?;
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+/// The currently playing track together with the upcoming playback queue.
+///
+/// Podcast episodes aren't modeled here (there's no episode type to put them
+/// in), so any episode that shows up as currently playing or queued is
+/// silently dropped rather than failing the whole response to deserialize.
+#[derive(Clone, Data, Deserialize)]
+pub struct Queue {
+    #[serde(deserialize_with = "deserialize_track_only")]
+    pub currently_playing: Option<Arc<Track>>,
+    #[serde(deserialize_with = "deserialize_tracks_only")]
+    pub queue: Vector<Arc<Track>>,
+}
+
+/// A queue entry that may be a track or, e.g., a podcast episode; only the
+/// track case is kept, the same tolerant-parsing approach `OptionalTrack`
+/// uses for playlist items.
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum QueueItem {
+    Track(Arc<Track>),
+    Other(serde_json::Value),
+}
+
+fn deserialize_track_only<'de, D>(deserializer: D) -> Result<Option<Arc<Track>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let item: Option<QueueItem> = Deserialize::deserialize(deserializer)?;
+    Ok(item.and_then(|item| match item {
+        QueueItem::Track(track) => Some(track),
+        QueueItem::Other(_) => None,
+    }))
+}
+
+fn deserialize_tracks_only<'de, D>(deserializer: D) -> Result<Vector<Arc<Track>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let items: Vector<QueueItem> = Deserialize::deserialize(deserializer)?;
+    Ok(items
+        .into_iter()
+        .filter_map(|item| match item {
+            QueueItem::Track(track) => Some(track),
+            QueueItem::Other(_) => None,
+        })
+        .collect())
+}
+
+/// A Spotify Connect device available for playback.
+#[derive(Clone, Data, Deserialize)]
+pub struct Device {
+    pub id: Option<Arc<str>>,
+    pub name: Arc<str>,
+    #[serde(rename = "type")]
+    pub device_type: Arc<str>,
+    pub is_active: bool,
+    pub volume_percent: Option<u32>,
+}
+
+/// A browse category, e.g. "Podcasts" or "Mood", used to group playlists in
+/// the browse/discovery section.
+#[derive(Clone, Data, Deserialize)]
+pub struct Category {
+    pub id: Arc<str>,
+    pub name: Arc<str>,
+    pub icons: Vector<CategoryIcon>,
+}
+
+#[derive(Clone, Data, Deserialize)]
+pub struct CategoryIcon {
+    pub url: Arc<str>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// Tests whether `country` (a 2-character ISO code) occurs in `list`, a
+/// string of concatenated 2-character country codes, as used by Spotify's
+/// market restriction data.
+fn countrylist_contains(list: &str, country: &str) -> bool {
+    list.as_bytes()
+        .chunks_exact(2)
+        .any(|code| code == country.as_bytes())
+}
+
+/// Market restrictions for a track or album, given as forbidden/allowed
+/// country lists.  An item is playable in a country when the country is not
+/// in the forbidden list and, if an allowed list is present, is in it.
+#[derive(Clone, Data, Deserialize)]
+pub struct Restrictions {
+    #[serde(default)]
+    pub countries_allowed: Option<Arc<str>>,
+    #[serde(default)]
+    pub countries_forbidden: Option<Arc<str>>,
+}
+
+impl Restrictions {
+    pub fn is_available_in(&self, country: &str) -> bool {
+        let forbidden = self
+            .countries_forbidden
+            .as_deref()
+            .map_or(false, |list| countrylist_contains(list, country));
+        let allowed = self
+            .countries_allowed
+            .as_deref()
+            .map_or(true, |list| countrylist_contains(list, country));
+        !forbidden && allowed
+    }
+}
+
+/// The market availability fields Spotify attaches to tracks and albums:
+/// either a plain list of available markets, or, for catalogues with
+/// restriction data, an explicit forbidden/allowed breakdown.
+#[derive(Clone, Data, Deserialize)]
+pub struct Availability {
+    #[serde(default)]
+    pub available_markets: Option<Vector<Arc<str>>>,
+    #[serde(default)]
+    pub restrictions: Option<Restrictions>,
+    /// Set instead of `available_markets`/`restrictions` when the request
+    /// supplied an explicit `market` (e.g. `market=from_token`): Spotify
+    /// resolves availability for that market itself and omits the other two
+    /// fields entirely, so they can't be used to tell playable apart from
+    /// unplayable in that case.
+    #[serde(default)]
+    pub is_playable: Option<bool>,
+}
+
+impl Availability {
+    pub fn is_available_in(&self, country: &str) -> bool {
+        if let Some(is_playable) = self.is_playable {
+            return is_playable;
+        }
+        if let Some(restrictions) = &self.restrictions {
+            return restrictions.is_available_in(country);
+        }
+        self.available_markets.as_ref().map_or(true, |markets| {
+            markets.iter().any(|market| market.as_ref() == country)
+        })
+    }
+}
+
+/// Wraps an item together with the market availability fields found
+/// alongside it in the same API response object.
+#[derive(Clone, Data, Deserialize)]
+struct WithAvailability<T: Clone + Data> {
+    #[serde(flatten)]
+    item: T,
+    #[serde(flatten)]
+    availability: Availability,
+}
+
+impl<T: Clone + Data> WithAvailability<T> {
+    fn is_available_in(&self, country: &str) -> bool {
+        self.availability.is_available_in(country)
+    }
+}
+
 pub struct WebApi {
     session: SessionService,
     agent: Agent,
     cache: WebApiCache,
     token_provider: TokenProvider,
+    user_country: OnceCell<Arc<str>>,
+    hide_unavailable: AtomicBool,
 }
 
 impl WebApi {
@@ -47,6 +409,43 @@ impl WebApi {
             agent,
             cache: WebApiCache::new(cache_base),
             token_provider: TokenProvider::new(),
+            user_country: OnceCell::new(),
+            hide_unavailable: AtomicBool::new(false),
+        }
+    }
+
+    /// Enable or disable dropping tracks/albums that are not available in the
+    /// user's market from list results.
+    pub fn set_hide_unavailable(&self, hide: bool) {
+        self.hide_unavailable.store(hide, Ordering::Relaxed);
+    }
+
+    fn hide_unavailable(&self) -> bool {
+        self.hide_unavailable.load(Ordering::Relaxed)
+    }
+
+    /// The user's country, resolved once from their profile and cached for
+    /// subsequent availability checks.
+    fn user_country(&self) -> Result<Arc<str>, Error> {
+        if let Some(country) = self.user_country.get() {
+            return Ok(country.clone());
+        }
+        let country = self.get_user_profile()?.country.ok_or_else(|| {
+            Error::WebApiError(
+                "User profile has no country (missing user-read-private scope?)".to_string(),
+            )
+        })?;
+        let _ = self.user_country.set(country.clone());
+        Ok(country)
+    }
+
+    /// If hiding unavailable results is enabled, the user's resolved
+    /// country; `None` means skip the availability filter.
+    fn filter_country(&self) -> Result<Option<Arc<str>>, Error> {
+        if self.hide_unavailable() {
+            Ok(Some(self.user_country()?))
+        } else {
+            Ok(None)
         }
     }
 
@@ -58,19 +457,32 @@ impl WebApi {
         Ok(token.token)
     }
 
-    fn request(&self, method: &str, path: impl Display) -> Result<Request, Error> {
+    fn request_with_host(
+        &self,
+        method: &str,
+        host: &str,
+        path: impl Display,
+    ) -> Result<Request, Error> {
         let token = self.access_token()?;
         let request = self
             .agent
-            .request(method, &format!("https://api.spotify.com/{}", path))
+            .request(method, &format!("https://{}/{}", host, path))
             .set("Authorization", &format!("Bearer {}", &token));
         Ok(request)
     }
 
+    fn request(&self, method: &str, path: impl Display) -> Result<Request, Error> {
+        self.request_with_host(method, API_HOST, path)
+    }
+
     fn get(&self, path: impl Display) -> Result<Request, Error> {
         self.request("GET", path)
     }
 
+    fn post(&self, path: impl Display) -> Result<Request, Error> {
+        self.request("POST", path)
+    }
+
     fn put(&self, path: impl Display) -> Result<Request, Error> {
         self.request("PUT", path)
     }
@@ -79,6 +491,12 @@ impl WebApi {
         self.request("DELETE", path)
     }
 
+    /// Like `get()`, but against the internal spclient host rather than the
+    /// public Web API.
+    fn get_spclient(&self, path: impl Display) -> Result<Request, Error> {
+        self.request_with_host("GET", SPCLIENT_HOST, path)
+    }
+
     fn with_retry(f: impl Fn() -> Result<Response, Error>) -> Result<Response, Error> {
         loop {
             let response = f()?;
@@ -104,6 +522,13 @@ impl WebApi {
         Ok(())
     }
 
+    /// Send a request with a serialized JSON body, throw away the response
+    /// body.  Use for POST/PUT requests that carry a payload.
+    fn send_json<T: Serialize>(&self, request: Request, body: &T) -> Result<(), Error> {
+        let _response = Self::with_retry(|| Ok(request.clone().send_json(body)?))?;
+        Ok(())
+    }
+
     /// Send a request and return the deserialized JSON body.  Use for GET
     /// requests.
     fn load<T: DeserializeOwned>(&self, request: Request) -> Result<T, Error> {
@@ -138,39 +563,187 @@ impl WebApi {
         }
     }
 
-    /// Load a paginated result set by sending `request` with added pagination
-    /// parameters and return the aggregated results.  Use with GET requests.
+    /// Load a single page of an offset/limit-paginated endpoint.  This is the
+    /// primitive [`Paginator`] drives; call it directly only when a single
+    /// page (rather than the full, lazily-fetched result set) is needed.
+    fn load_page<T: DeserializeOwned>(
+        &self,
+        request: Request,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Page<T>, Error> {
+        let req = request
+            .query("limit", &limit.to_string())
+            .query("offset", &offset.to_string());
+        self.load(req)
+    }
+
+    /// Lazily paginate `request` through its offset/limit pages, fetching
+    /// each page only as the returned iterator is advanced.
+    fn paginate<T: DeserializeOwned + Clone>(
+        &self,
+        request: Request,
+        page_size: usize,
+    ) -> Paginator<'_, T> {
+        Paginator::by_offset(self, request, page_size)
+    }
+
+    /// Lazily paginate `request` through its `cursors.after` pages, fetching
+    /// each page only as the returned iterator is advanced.
+    fn paginate_by_cursor<T: DeserializeOwned + Clone>(
+        &self,
+        request: Request,
+        page_size: usize,
+    ) -> Paginator<'_, T> {
+        Paginator::by_cursor(self, request, page_size)
+    }
+
+    /// Load every page of a paginated result set and return the aggregated
+    /// results.  Use with GET requests.
     fn load_all_pages<T: DeserializeOwned + Clone>(
         &self,
         request: Request,
     ) -> Result<Vector<T>, Error> {
-        // TODO: Some result sets, like very long playlists and saved tracks/albums can
-        // be very big.  Implement virtualized scrolling and lazy-loading of results.
-        const PAGED_ITEMS_LIMIT: usize = 200;
+        let mut results = Vector::new();
+        for page in self.paginate(request, 50) {
+            results.extend(page?);
+        }
+        Ok(results)
+    }
 
+    /// Like `load_all_pages`, but for endpoints whose page is nested under a
+    /// named key in the response object (e.g. browse endpoints, which
+    /// respond with `{"categories": {"items": [...], ...}}`) rather than
+    /// being the response root itself.
+    fn load_all_pages_keyed<W: DeserializeOwned, T: Clone>(
+        &self,
+        request: Request,
+        unwrap: impl Fn(W) -> Page<T>,
+    ) -> Result<Vector<T>, Error> {
         let mut results = Vector::new();
-        let mut limit = 50;
         let mut offset = 0;
+        let limit = 50;
         loop {
             let req = request
                 .clone()
                 .query("limit", &limit.to_string())
                 .query("offset", &offset.to_string());
-            let page: Page<T> = self.load(req)?;
-
+            let page = unwrap(self.load(req)?);
+            let fetched = offset + page.items.len();
+            let is_last = page.items.is_empty() || fetched >= page.total;
             results.extend(page.items);
-
-            if page.total > results.len() && results.len() < PAGED_ITEMS_LIMIT {
-                limit = page.limit;
-                offset = page.offset + page.limit;
-            } else {
+            if is_last {
                 break;
             }
+            offset = fetched;
         }
         Ok(results)
     }
 }
 
+/// A single page of a cursor-paginated result set, as returned by endpoints
+/// like the recently-played-tracks listing (`next` URL / `cursors.after`).
+#[derive(Deserialize)]
+struct CursorPage<T> {
+    items: Vector<T>,
+    #[serde(default)]
+    cursors: Option<Cursors>,
+}
+
+#[derive(Deserialize)]
+struct Cursors {
+    after: Option<Arc<str>>,
+}
+
+/// Which pagination scheme a [`Paginator`] is driving: Spotify's usual
+/// offset/limit paging, or the cursor-based (`after`) paging some endpoints,
+/// such as recently-played tracks, use instead.
+enum PaginatorState {
+    Offset {
+        offset: usize,
+        limit: usize,
+    },
+    Cursor {
+        after: Option<Arc<str>>,
+        limit: usize,
+    },
+}
+
+/// Iterator that fetches successive pages of a paginated endpoint on
+/// demand, rather than eagerly aggregating the whole result set up front.
+/// Stops once Spotify reports no more items are available.
+struct Paginator<'a, T> {
+    webapi: &'a WebApi,
+    request: Request,
+    state: Option<PaginatorState>,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T> Paginator<'a, T> {
+    fn by_offset(webapi: &'a WebApi, request: Request, limit: usize) -> Self {
+        Self {
+            webapi,
+            request,
+            state: Some(PaginatorState::Offset { offset: 0, limit }),
+            _marker: PhantomData,
+        }
+    }
+
+    fn by_cursor(webapi: &'a WebApi, request: Request, limit: usize) -> Self {
+        Self {
+            webapi,
+            request,
+            state: Some(PaginatorState::Cursor { after: None, limit }),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: DeserializeOwned + Clone> Iterator for Paginator<'a, T> {
+    type Item = Result<Vector<T>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let state = self.state.take()?;
+        let (items, next_state) = match state {
+            PaginatorState::Offset { offset, limit } => {
+                let page: Page<T> = match self.webapi.load_page(self.request.clone(), offset, limit)
+                {
+                    Ok(page) => page,
+                    Err(err) => return Some(Err(err)),
+                };
+                let fetched = offset + page.items.len();
+                let next_state = (fetched < page.total && !page.items.is_empty()).then_some(
+                    PaginatorState::Offset {
+                        offset: fetched,
+                        limit,
+                    },
+                );
+                (page.items, next_state)
+            }
+            PaginatorState::Cursor { after, limit } => {
+                let mut req = self.request.clone().query("limit", &limit.to_string());
+                if let Some(after) = &after {
+                    req = req.query("after", after);
+                }
+                let page: CursorPage<T> = match self.webapi.load(req) {
+                    Ok(page) => page,
+                    Err(err) => return Some(Err(err)),
+                };
+                let next_after = page.cursors.and_then(|cursors| cursors.after);
+                let next_state = next_after.filter(|_| !page.items.is_empty()).map(|after| {
+                    PaginatorState::Cursor {
+                        after: Some(after),
+                        limit,
+                    }
+                });
+                (page.items, next_state)
+            }
+        };
+        self.state = next_state;
+        Some(Ok(items))
+    }
+}
+
 static GLOBAL_WEBAPI: OnceCell<Arc<WebApi>> = OnceCell::new();
 
 /// Global instance.
@@ -199,14 +772,14 @@ impl WebApi {
 /// Artist endpoints.
 impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/artists/get-artist/
-    pub fn get_artist(&self, id: &str) -> Result<Artist, Error> {
+    pub fn get_artist(&self, id: &ArtistId) -> Result<Artist, Error> {
         let request = self.get(format!("v1/artists/{}", id))?;
-        let result = self.load_cached(request, "artist", id)?;
+        let result = self.load_cached(request, "artist", id.id())?;
         Ok(result.data)
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/artists/get-artists-albums/
-    pub fn get_artist_albums(&self, id: &str) -> Result<ArtistAlbums, Error> {
+    pub fn get_artist_albums(&self, id: &ArtistId) -> Result<ArtistAlbums, Error> {
         let request = self
             .get(format!("v1/artists/{}/albums", id))?
             .query("market", "from_token");
@@ -230,7 +803,7 @@ impl WebApi {
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/artists/get-artists-top-tracks/
-    pub fn get_artist_top_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
+    pub fn get_artist_top_tracks(&self, id: &ArtistId) -> Result<Vector<Arc<Track>>, Error> {
         #[derive(Deserialize)]
         struct Tracks {
             tracks: Vector<Arc<Track>>,
@@ -244,14 +817,14 @@ impl WebApi {
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/artists/get-related-artists/
-    pub fn get_related_artists(&self, id: &str) -> Result<Cached<Vector<Artist>>, Error> {
+    pub fn get_related_artists(&self, id: &ArtistId) -> Result<Cached<Vector<Artist>>, Error> {
         #[derive(Clone, Data, Deserialize)]
         struct Artists {
             artists: Vector<Artist>,
         }
 
         let request = self.get(format!("v1/artists/{}/related-artists", id))?;
-        let result: Cached<Artists> = self.load_cached(request, "related-artists", id)?;
+        let result: Cached<Artists> = self.load_cached(request, "related-artists", id.id())?;
         Ok(result.map(|result| result.artists))
     }
 }
@@ -259,11 +832,11 @@ impl WebApi {
 /// Album endpoints.
 impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/albums/get-album/
-    pub fn get_album(&self, id: &str) -> Result<Cached<Arc<Album>>, Error> {
+    pub fn get_album(&self, id: &AlbumId) -> Result<Cached<Arc<Album>>, Error> {
         let request = self
             .get(format!("v1/albums/{}", id))?
             .query("market", "from_token");
-        let result = self.load_cached(request, "album", id)?;
+        let result = self.load_cached(request, "album", id.id())?;
         Ok(result)
     }
 }
@@ -271,7 +844,7 @@ impl WebApi {
 /// Track endpoints.
 impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-track
-    pub fn get_track(&self, id: &str) -> Result<Arc<Track>, Error> {
+    pub fn get_track(&self, id: &TrackId) -> Result<Arc<Track>, Error> {
         let request = self
             .get(format!("v1/tracks/{}", id))?
             .query("market", "from_token");
@@ -286,61 +859,136 @@ impl WebApi {
     pub fn get_saved_albums(&self) -> Result<Vector<Arc<Album>>, Error> {
         #[derive(Clone, Deserialize)]
         struct SavedAlbum {
-            album: Arc<Album>,
+            album: WithAvailability<Arc<Album>>,
         }
 
         let request = self.get("v1/me/albums")?.query("market", "from_token");
+        let country = self.filter_country()?;
 
         Ok(self
             .load_all_pages(request)?
             .into_iter()
-            .map(|item: SavedAlbum| item.album)
+            .filter(|item: &SavedAlbum| {
+                country
+                    .as_deref()
+                    .map_or(true, |country| item.album.is_available_in(country))
+            })
+            .map(|item: SavedAlbum| item.album.item)
             .collect())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/save-albums-user/
-    pub fn save_album(&self, id: &str) -> Result<(), Error> {
-        let request = self.put("v1/me/albums")?.query("ids", id);
+    pub fn save_album(&self, id: &AlbumId) -> Result<(), Error> {
+        let request = self.put("v1/me/albums")?.query("ids", id.id());
         self.send_empty_json(request)?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/remove-albums-user/
-    pub fn unsave_album(&self, id: &str) -> Result<(), Error> {
-        let request = self.delete("v1/me/albums")?.query("ids", id);
+    pub fn unsave_album(&self, id: &AlbumId) -> Result<(), Error> {
+        let request = self.delete("v1/me/albums")?.query("ids", id.id());
         self.send_empty_json(request)?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/get-users-saved-tracks/
-    pub fn get_saved_tracks(&self) -> Result<Vector<Arc<Track>>, Error> {
+    pub fn get_saved_tracks(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<Vector<Arc<Track>>, Error>> + '_, Error> {
         #[derive(Clone, Deserialize)]
         struct SavedTrack {
-            track: Arc<Track>,
+            track: WithAvailability<Arc<Track>>,
         }
 
         let request = self.get("v1/me/tracks")?.query("market", "from_token");
+        let country = self.filter_country()?;
 
-        Ok(self
-            .load_all_pages(request)?
-            .into_iter()
-            .map(|item: SavedTrack| item.track)
-            .collect())
+        Ok(self.paginate::<SavedTrack>(request, 50).map(move |page| {
+            Ok(page?
+                .into_iter()
+                .filter(|item: &SavedTrack| {
+                    country
+                        .as_deref()
+                        .map_or(true, |country| item.track.is_available_in(country))
+                })
+                .map(|item: SavedTrack| item.track.item)
+                .collect())
+        }))
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/save-tracks-user/
-    pub fn save_track(&self, id: &str) -> Result<(), Error> {
-        let request = self.put("v1/me/tracks")?.query("ids", id);
+    pub fn save_track(&self, id: &TrackId) -> Result<(), Error> {
+        let request = self.put("v1/me/tracks")?.query("ids", id.id());
         self.send_empty_json(request)?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/remove-tracks-user/
-    pub fn unsave_track(&self, id: &str) -> Result<(), Error> {
-        let request = self.delete("v1/me/tracks")?.query("ids", id);
+    pub fn unsave_track(&self, id: &TrackId) -> Result<(), Error> {
+        let request = self.delete("v1/me/tracks")?.query("ids", id.id());
+        self.send_empty_json(request)?;
+        Ok(())
+    }
+}
+
+/// Player endpoints.
+impl WebApi {
+    // https://developer.spotify.com/documentation/web-api/reference/player/add-to-queue/
+    pub fn add_to_queue(&self, item: &Playable) -> Result<(), Error> {
+        let request = self.post("v1/me/player/queue")?.query("uri", &item.uri());
         self.send_empty_json(request)?;
         Ok(())
     }
+
+    // https://developer.spotify.com/documentation/web-api/reference/player/get-queue/
+    pub fn get_queue(&self) -> Result<Queue, Error> {
+        let request = self.get("v1/me/player/queue")?;
+        let result = self.load(request)?;
+        Ok(result)
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/player/transfer-a-users-playback/
+    pub fn transfer_playback(&self, device_id: &str) -> Result<(), Error> {
+        #[derive(Serialize)]
+        struct TransferPlaybackBody<'a> {
+            device_ids: [&'a str; 1],
+        }
+
+        let request = self.put("v1/me/player")?;
+        let body = TransferPlaybackBody {
+            device_ids: [device_id],
+        };
+        self.send_json(request, &body)?;
+        Ok(())
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/player/get-a-users-available-devices/
+    pub fn get_available_devices(&self) -> Result<Vector<Device>, Error> {
+        #[derive(Deserialize)]
+        struct Devices {
+            devices: Vector<Device>,
+        }
+
+        let request = self.get("v1/me/player/devices")?;
+        let result: Devices = self.load(request)?;
+        Ok(result.devices)
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/player/get-recently-played/
+    pub fn get_recently_played_tracks(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<Vector<Arc<Track>>, Error>> + '_, Error> {
+        #[derive(Clone, Deserialize)]
+        struct PlayHistoryItem {
+            track: Arc<Track>,
+        }
+
+        let request = self.get("v1/me/player/recently-played")?;
+
+        Ok(self
+            .paginate_by_cursor::<PlayHistoryItem>(request, 50)
+            .map(|page| Ok(page?.into_iter().map(|item| item.track).collect())))
+    }
 }
 
 /// View endpoints.
@@ -361,6 +1009,64 @@ impl WebApi {
     }
 }
 
+/// Browse endpoints.
+impl WebApi {
+    // https://developer.spotify.com/documentation/web-api/reference/browse/get-categories/
+    pub fn get_categories(&self) -> Result<Vector<Category>, Error> {
+        #[derive(Deserialize)]
+        struct Categories {
+            categories: Page<Category>,
+        }
+
+        let request = self.get("v1/browse/categories")?;
+        self.load_all_pages_keyed(request, |result: Categories| result.categories)
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/browse/get-a-categories-playlists/
+    pub fn get_category_playlists(&self, category_id: &str) -> Result<Vector<Playlist>, Error> {
+        #[derive(Deserialize)]
+        struct CategoryPlaylists {
+            playlists: Page<Playlist>,
+        }
+
+        let request = self.get(format!("v1/browse/categories/{}/playlists", category_id))?;
+        self.load_all_pages_keyed(request, |result: CategoryPlaylists| result.playlists)
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/browse/get-featured-playlists/
+    pub fn get_featured_playlists(&self) -> Result<Vector<Playlist>, Error> {
+        #[derive(Deserialize)]
+        struct FeaturedPlaylists {
+            playlists: Page<Playlist>,
+        }
+
+        let request = self.get("v1/browse/featured-playlists")?;
+        self.load_all_pages_keyed(request, |result: FeaturedPlaylists| result.playlists)
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/browse/get-new-releases/
+    pub fn get_new_releases(&self) -> Result<Vector<Arc<Album>>, Error> {
+        #[derive(Deserialize)]
+        struct NewReleases {
+            albums: Page<WithAvailability<Arc<Album>>>,
+        }
+
+        let request = self.get("v1/browse/new-releases")?;
+        let country = self.filter_country()?;
+
+        Ok(self
+            .load_all_pages_keyed(request, |result: NewReleases| result.albums)?
+            .into_iter()
+            .filter(|item: &WithAvailability<Arc<Album>>| {
+                country
+                    .as_deref()
+                    .map_or(true, |country| item.is_available_in(country))
+            })
+            .map(|item| item.item)
+            .collect())
+    }
+}
+
 /// Playlist endpoints.
 impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-a-list-of-current-users-playlists
@@ -371,14 +1077,17 @@ impl WebApi {
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-playlist
-    pub fn get_playlist(&self, id: &str) -> Result<Playlist, Error> {
+    pub fn get_playlist(&self, id: &PlaylistId) -> Result<Playlist, Error> {
         let request = self.get(format!("v1/playlists/{}", id))?;
         let result = self.load(request)?;
         Ok(result)
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-playlists-tracks
-    pub fn get_playlist_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
+    pub fn get_playlist_tracks(
+        &self,
+        id: &PlaylistId,
+    ) -> Result<impl Iterator<Item = Result<Vector<Arc<Track>>, Error>> + '_, Error> {
         #[derive(Clone, Deserialize)]
         struct PlaylistItem {
             is_local: bool,
@@ -388,10 +1097,19 @@ impl WebApi {
         // Spotify API likes to return _really_ bogus data for local tracks. Much better
         // would be to ignore parsing this completely if `is_local` is true, but this
         // will do as well.
+        //
+        // Stacking `#[serde(untagged)]` over a variant that itself uses
+        // `#[serde(flatten)]` (via `WithAvailability`) round-trips fine here:
+        // the sharp edge with flatten is combining it with an *internally*
+        // tagged enum (`#[serde(tag = "...")]`), which can't buffer the
+        // remaining fields while also peeking at the tag. An untagged enum
+        // buffers the whole object as `serde_json::Value` up front and tries
+        // each variant against that buffer in turn, which flatten has no
+        // trouble deserializing from.
         #[derive(Clone, Deserialize)]
         #[serde(untagged)]
         enum OptionalTrack {
-            Track(Arc<Track>),
+            Track(WithAvailability<Arc<Track>>),
             Json(serde_json::Value),
         }
 
@@ -399,18 +1117,26 @@ impl WebApi {
             .get(format!("v1/playlists/{}/tracks", id))?
             .query("marker", "from_token")
             .query("additional_types", "track");
-        let result: Vector<PlaylistItem> = self.load_all_pages(request)?;
+        let country = self.filter_country()?;
 
-        Ok(result
-            .into_iter()
-            .filter_map(|item| match item {
-                PlaylistItem {
-                    is_local: false,
-                    track: OptionalTrack::Track(track),
-                } => Some(track),
-                _ => None,
-            })
-            .collect())
+        Ok(self.paginate::<PlaylistItem>(request, 50).map(move |page| {
+            Ok(page?
+                .into_iter()
+                .filter_map(|item| match item {
+                    PlaylistItem {
+                        is_local: false,
+                        track: OptionalTrack::Track(track),
+                    } => Some(track),
+                    _ => None,
+                })
+                .filter(|track| {
+                    country
+                        .as_deref()
+                        .map_or(true, |country| track.is_available_in(country))
+                })
+                .map(|track| track.item)
+                .collect())
+        }))
     }
 }
 
@@ -421,8 +1147,8 @@ impl WebApi {
         #[derive(Deserialize)]
         struct ApiSearchResults {
             artists: Option<Page<Artist>>,
-            albums: Option<Page<Arc<Album>>>,
-            tracks: Option<Page<Arc<Track>>>,
+            albums: Option<Page<WithAvailability<Arc<Album>>>>,
+            tracks: Option<Page<WithAvailability<Arc<Track>>>>,
             playlists: Option<Page<Playlist>>,
         }
 
@@ -432,10 +1158,31 @@ impl WebApi {
             .query("type", "artist,album,track,playlist")
             .query("marker", "from_token");
         let result: ApiSearchResults = self.load(request)?;
+        let country = self.filter_country()?;
 
         let artists = result.artists.map_or_else(Vector::new, |page| page.items);
-        let albums = result.albums.map_or_else(Vector::new, |page| page.items);
-        let tracks = result.tracks.map_or_else(Vector::new, |page| page.items);
+        let albums = result
+            .albums
+            .map_or_else(Vector::new, |page| page.items)
+            .into_iter()
+            .filter(|item| {
+                country
+                    .as_deref()
+                    .map_or(true, |country| item.is_available_in(country))
+            })
+            .map(|item| item.item)
+            .collect();
+        let tracks = result
+            .tracks
+            .map_or_else(Vector::new, |page| page.items)
+            .into_iter()
+            .filter(|item| {
+                country
+                    .as_deref()
+                    .map_or(true, |country| item.is_available_in(country))
+            })
+            .map(|item| item.item)
+            .collect();
         let playlists = result.playlists.map_or_else(Vector::new, |page| page.items);
         Ok(SearchResults {
             query: query.into(),
@@ -446,16 +1193,29 @@ impl WebApi {
         })
     }
 
+    // `SpotifyUrl` has already parsed the `spotify:kind:id` URI or
+    // `open.spotify.com/kind/id` URL; the ids it hands us are bare, so we
+    // wrap them with `Id::from_bare_id` rather than re-parsing them through
+    // `Id::parse`.
     pub fn load_spotify_link(&self, link: &SpotifyUrl) -> Result<Nav, Error> {
         let nav = match link {
-            SpotifyUrl::Playlist(id) => Nav::PlaylistDetail(self.get_playlist(id)?.link()),
-            SpotifyUrl::Artist(id) => Nav::ArtistDetail(self.get_artist(id)?.link()),
-            SpotifyUrl::Album(id) => Nav::AlbumDetail(self.get_album(id)?.data.link()),
+            SpotifyUrl::Playlist(id) => {
+                Nav::PlaylistDetail(self.get_playlist(&PlaylistId::from_bare_id(id)?)?.link())
+            }
+            SpotifyUrl::Artist(id) => {
+                Nav::ArtistDetail(self.get_artist(&ArtistId::from_bare_id(id)?)?.link())
+            }
+            SpotifyUrl::Album(id) => {
+                Nav::AlbumDetail(self.get_album(&AlbumId::from_bare_id(id)?)?.data.link())
+            }
             SpotifyUrl::Track(id) => Nav::AlbumDetail(
                 // TODO: We should highlight the exact track in the album.
-                self.get_track(id)?.album.clone().ok_or_else(|| {
-                    Error::WebApiError("Track was found but has no album".to_string())
-                })?,
+                self.get_track(&TrackId::from_bare_id(id)?)?
+                    .album
+                    .clone()
+                    .ok_or_else(|| {
+                        Error::WebApiError("Track was found but has no album".to_string())
+                    })?,
             ),
         };
         Ok(nav)
@@ -469,7 +1229,15 @@ impl WebApi {
         &self,
         data: Arc<RecommendationsRequest>,
     ) -> Result<Recommendations, Error> {
-        let seed_artists = data.seed_artists.iter().map(|link| &link.id).join(", ");
+        // `seed_tracks` is already a `Vector<TrackId>`; route `seed_artists`
+        // through the same typed id layer instead of joining its raw `id`
+        // field, so both seed lists are validated and formatted the same way.
+        let seed_artists = data
+            .seed_artists
+            .iter()
+            .map(|link| ArtistId::from_bare_id(&link.id).map(|id| id.to_base62()))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(", ");
         let seed_tracks = data
             .seed_tracks
             .iter()
@@ -519,13 +1287,29 @@ impl WebApi {
 /// Track endpoints.
 impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/tracks/get-audio-analysis/
-    pub fn _get_audio_analysis(&self, track_id: &str) -> Result<AudioAnalysis, Error> {
+    pub fn _get_audio_analysis(&self, track_id: &TrackId) -> Result<AudioAnalysis, Error> {
         let request = self.get(format!("v1/audio-analysis/{}", track_id))?;
-        let result = self.load_cached(request, "audio-analysis", track_id)?;
+        let result = self.load_cached(request, "audio-analysis", track_id.id())?;
         Ok(result.data)
     }
 }
 
+/// Lyrics endpoints.
+impl WebApi {
+    // https://spclient.wg.spotify.com/color-lyrics/v2/track/{id}
+    pub fn get_track_lyrics(&self, track_id: &TrackId) -> Result<Lyrics, Error> {
+        #[derive(Clone, Data, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ColorLyrics {
+            lyrics: Lyrics,
+        }
+
+        let request = self.get_spclient(format!("color-lyrics/v2/track/{}", track_id))?;
+        let result: Cached<ColorLyrics> = self.load_cached(request, "lyrics", track_id.id())?;
+        Ok(result.data.lyrics)
+    }
+}
+
 /// Image endpoints.
 impl WebApi {
     pub fn get_image(&self, uri: &str) -> Result<image::DynamicImage, Error> {